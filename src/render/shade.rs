@@ -14,7 +14,8 @@
 
 //! Shader parameter handling.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell, RefMut};
+use std::ops::{Deref, DerefMut};
 use device::shade;
 use device::shade::UniformValue;
 use device::{handle, Resources};
@@ -24,35 +25,156 @@ pub use device::shade::{Stage, CreateShaderError};
 
 
 macro_rules! uniform {
-    ($ty_src:ty, $ty_dst:ident) => {
+    ($ty_src:ty, $ty_dst:ident, $base:ident, $container:expr) => {
         impl Into<UniformValue> for $ty_src {
             fn into(self) -> UniformValue {
                 UniformValue::$ty_dst(self)
             }
         }
+
+        impl<R: Resources> Parameter<R> for $ty_src {
+            fn check_uniform(var: &shade::UniformVar) -> bool {
+                var.base_type == shade::BaseType::$base &&
+                var.container == $container
+            }
+
+            fn put(&self, id: ParameterId, storage: &mut ParamStorage<R>) {
+                storage.uniforms[id as usize] = Some(self.clone().into());
+            }
+        }
     }
 }
 
-uniform!(i32, I32);
-uniform!(f32, F32);
+uniform!(i32, I32, I32, shade::ContainerType::Single);
+uniform!(f32, F32, F32, shade::ContainerType::Single);
+
+uniform!([i32; 2], I32Vector2, I32, shade::ContainerType::Vector(2));
+uniform!([i32; 3], I32Vector3, I32, shade::ContainerType::Vector(3));
+uniform!([i32; 4], I32Vector4, I32, shade::ContainerType::Vector(4));
+
+uniform!([f32; 2], F32Vector2, F32, shade::ContainerType::Vector(2));
+uniform!([f32; 3], F32Vector3, F32, shade::ContainerType::Vector(3));
+uniform!([f32; 4], F32Vector4, F32, shade::ContainerType::Vector(4));
+
+uniform!([[f32; 2]; 2], F32Matrix2, F32, shade::ContainerType::Matrix(2, 2));
+uniform!([[f32; 3]; 3], F32Matrix3, F32, shade::ContainerType::Matrix(3, 3));
+uniform!([[f32; 4]; 4], F32Matrix4, F32, shade::ContainerType::Matrix(4, 4));
+
+uniform!([[f32; 3]; 2], F32Matrix2x3, F32, shade::ContainerType::Matrix(2, 3));
+uniform!([[f32; 4]; 2], F32Matrix2x4, F32, shade::ContainerType::Matrix(2, 4));
+uniform!([[f32; 2]; 3], F32Matrix3x2, F32, shade::ContainerType::Matrix(3, 2));
+uniform!([[f32; 4]; 3], F32Matrix3x4, F32, shade::ContainerType::Matrix(3, 4));
+uniform!([[f32; 2]; 4], F32Matrix4x2, F32, shade::ContainerType::Matrix(4, 2));
+uniform!([[f32; 3]; 4], F32Matrix4x3, F32, shade::ContainerType::Matrix(4, 3));
+
+uniform!(u32, U32, U32, shade::ContainerType::Single);
+
+uniform!([u32; 2], U32Vector2, U32, shade::ContainerType::Vector(2));
+uniform!([u32; 3], U32Vector3, U32, shade::ContainerType::Vector(3));
+uniform!([u32; 4], U32Vector4, U32, shade::ContainerType::Vector(4));
+
+uniform!(bool, Bool, Bool, shade::ContainerType::Single);
+
+uniform!([bool; 2], BoolVector2, Bool, shade::ContainerType::Vector(2));
+uniform!([bool; 3], BoolVector3, Bool, shade::ContainerType::Vector(3));
+uniform!([bool; 4], BoolVector4, Bool, shade::ContainerType::Vector(4));
+
+uniform!(f64, F64, F64, shade::ContainerType::Single);
 
-uniform!([i32; 2], I32Vector2);
-uniform!([i32; 3], I32Vector3);
-uniform!([i32; 4], I32Vector4);
+uniform!([f64; 2], F64Vector2, F64, shade::ContainerType::Vector(2));
+uniform!([f64; 3], F64Vector3, F64, shade::ContainerType::Vector(3));
+uniform!([f64; 4], F64Vector4, F64, shade::ContainerType::Vector(4));
 
-uniform!([f32; 2], F32Vector2);
-uniform!([f32; 3], F32Vector3);
-uniform!([f32; 4], F32Vector4);
+/// Binds a fixed-size Rust array onto a GLSL uniform array (e.g.
+/// `uniform vec4 lights[16];`). Unlike `uniform!`, the shader is allowed to
+/// declare a *longer* array than the Rust side provides, since driver-side
+/// array uniforms are commonly over-sized; `check_uniform` rejects the
+/// opposite case, where the Rust array would overflow the declared slots.
+///
+/// `put` always writes exactly the Rust-side element count, never more;
+/// it does not pad out to the shader's declared length (`check_uniform`
+/// only guarantees ours is no *longer*). Any slots beyond what we upload
+/// simply keep their previous value on the driver side, same as any other
+/// uniform array left partially written.
+///
+/// Only a fixed list of array lengths gets a `Parameter` impl below (no
+/// const generics in this Rust), so a `shader_param` field whose length
+/// isn't in that list won't compile; add it to the invocation for the
+/// element type if a new length is needed.
+///
+/// Careful when doing so: a length/element combination that reproduces an
+/// already-`uniform!`'d array or matrix type (e.g. `$ty_elem = f32`,
+/// `$count = 4`, which is the same concrete type as `[f32; 4]` above) is a
+/// duplicate `impl Parameter<R> for ...` and fails to compile (`E0119`).
+/// Check the `uniform!` invocations above before adding a length here.
+macro_rules! uniform_array {
+    ($ty_elem:ty, $base:ident, $elem_container:expr, [$($count:expr),+]) => {
+        $(
+            impl<R: Resources> Parameter<R> for [$ty_elem; $count] {
+                fn check_uniform(var: &shade::UniformVar) -> bool {
+                    match var.container {
+                        shade::ContainerType::Array(n, ref elem) =>
+                            $count <= n &&
+                            var.base_type == shade::BaseType::$base &&
+                            **elem == $elem_container,
+                        _ => false,
+                    }
+                }
 
-uniform!([[f32; 2]; 2], F32Matrix2);
-uniform!([[f32; 3]; 3], F32Matrix3);
-uniform!([[f32; 4]; 4], F32Matrix4);
+                fn put(&self, id: ParameterId, storage: &mut ParamStorage<R>) {
+                    let values = self.iter().cloned().map(|v| v.into()).collect();
+                    storage.uniforms[id as usize] = Some(UniformValue::Array(values));
+                }
+            }
+        )+
+    }
+}
+
+// `4` is dropped from the first two lists: `[f32; 4]` and `[[f32; 4]; 4]`
+// already have a `Parameter` impl from `uniform!` above (as a vector and a
+// matrix, respectively), so generating another one here for `$count = 4`
+// would be a duplicate impl for the same type and fail to build. The third
+// list has no such collision, since nothing above hands out a `Parameter`
+// impl for `[[[f32; 4]; 4]; N]`.
+uniform_array!(f32, F32, shade::ContainerType::Single, [8, 16, 32, 64]);
+uniform_array!([f32; 4], F32, shade::ContainerType::Vector(4), [8, 16, 32, 64]);
+uniform_array!([[f32; 4]; 4], F32, shade::ContainerType::Matrix(4, 4), [4, 8, 16, 32, 64]);
 
 /// A texture parameter: consists of a texture handle with an optional sampler.
-/// Not all textures need a sampler (i.e. MSAA ones do not). Optimally, we'd want to
-/// encode this logic into the type system (TODO).
+/// Not all textures need a sampler (i.e. MSAA ones do not). Used by
+/// `ParamDictionary`, where the sampler requirement can only be known at
+/// runtime; `shader_param` structs should prefer `SampledTexture`/
+/// `UnsampledTexture` instead, which encode it in the type.
 pub type TextureParam<R: Resources> = (handle::Texture<R>, Option<handle::Sampler<R>>);
 
+/// A texture bound together with the sampler used to read it, for shader
+/// slots that declare a regular (non-multisampled) sampler.
+///
+/// `check_texture` only verifies the multisample flag, not the shader's
+/// declared sampler dimensionality (1D/2D/3D/Cube/...), and that's a real
+/// gap rather than a todo: `Parameter::check_texture` deliberately takes no
+/// `&self` (the same as `check_uniform`/`check_block`), so it can only ever
+/// compare the `SamplerVar` against something baked into the *type* at
+/// compile time, never against a particular `SampledTexture` value's own
+/// texture. Closing this would mean either carrying the dimension as a type
+/// parameter here (and on every other `Parameter` impl that would need to
+/// follow suit) or changing `check_texture`'s signature to take `&self`
+/// (affecting every `Parameter` impl in this file, not just textures) —
+/// out of scope for the type-encoded sampled/unsampled split this struct
+/// exists for. Treat dimensionality mismatches as still the caller's
+/// responsibility to avoid until that larger redesign happens.
+#[derive(Clone, Debug)]
+pub struct SampledTexture<R: Resources>(pub handle::Texture<R>, pub handle::Sampler<R>);
+
+/// A texture with no sampler attached, for multisampled shader slots (which
+/// GLSL reads with `texelFetch` and which take no sampler state at all).
+/// `check_texture` rejects any slot whose sampler is not multisampled.
+///
+/// As with `SampledTexture`, only the multisample flag is checked; see its
+/// doc comment for why declared sampler dimensionality isn't validated here.
+#[derive(Clone, Debug)]
+pub struct UnsampledTexture<R: Resources>(pub handle::Texture<R>);
+
 /// An error type on either the parameter storage or the program side
 #[derive(Clone, PartialEq, Debug)]
 pub enum ParameterError {
@@ -72,6 +194,98 @@ pub enum ParameterError {
     BadTexture(String),
 }
 
+/// A non-fatal issue found while linking parameters to a program. Unlike
+/// `ParameterError`, a warning does not prevent `create_link` from
+/// succeeding; it's meant to be logged by the caller during development.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ProgramWarning {
+    /// A parameter with this name exists, but the program being linked
+    /// against does not reference it (most likely optimized away by the
+    /// driver, or simply unused by this particular program).
+    InactiveUniform(String),
+    /// A block with this name exists, but the program being linked against
+    /// does not reference it (most likely optimized away by the driver, or
+    /// simply unused by this particular program).
+    InactiveBlock(String),
+    /// The bound uniform's shape (scalar/vector/matrix/array) matches what
+    /// the shader declared, but its base type does not (e.g. an `ivec`
+    /// bound where a `uvec` was expected). Close enough that linking can
+    /// proceed anyway; a shape mismatch, by contrast, is always fatal
+    /// (`ParameterError::BadUniform`).
+    TypeMismatch {
+        /// Name of the uniform.
+        name: String,
+        /// Base type reflected from the shader.
+        expected: shade::BaseType,
+        /// Base type of the value that was actually bound.
+        found: shade::BaseType,
+    },
+}
+
+/// Reflects the `BaseType` of a concrete `UniformValue`, for comparing
+/// against what a shader declared.
+fn uniform_base_type(value: &UniformValue) -> shade::BaseType {
+    match *value {
+        UniformValue::I32(_) |
+        UniformValue::I32Vector2(_) | UniformValue::I32Vector3(_) | UniformValue::I32Vector4(_) =>
+            shade::BaseType::I32,
+        UniformValue::F32(_) |
+        UniformValue::F32Vector2(_) | UniformValue::F32Vector3(_) | UniformValue::F32Vector4(_) |
+        UniformValue::F32Matrix2(_) | UniformValue::F32Matrix3(_) | UniformValue::F32Matrix4(_) |
+        UniformValue::F32Matrix2x3(_) | UniformValue::F32Matrix2x4(_) |
+        UniformValue::F32Matrix3x2(_) | UniformValue::F32Matrix3x4(_) |
+        UniformValue::F32Matrix4x2(_) | UniformValue::F32Matrix4x3(_) =>
+            shade::BaseType::F32,
+        UniformValue::U32(_) |
+        UniformValue::U32Vector2(_) | UniformValue::U32Vector3(_) | UniformValue::U32Vector4(_) =>
+            shade::BaseType::U32,
+        UniformValue::Bool(_) |
+        UniformValue::BoolVector2(_) | UniformValue::BoolVector3(_) | UniformValue::BoolVector4(_) =>
+            shade::BaseType::Bool,
+        UniformValue::F64(_) |
+        UniformValue::F64Vector2(_) | UniformValue::F64Vector3(_) | UniformValue::F64Vector4(_) =>
+            shade::BaseType::F64,
+        // An array's base type is that of its elements; an empty array
+        // carries no type information of its own, so default to F32.
+        UniformValue::Array(ref values) =>
+            values.first().map_or(shade::BaseType::F32, |v| uniform_base_type(v)),
+    }
+}
+
+/// Reflects the `ContainerType` (scalar/vector/matrix/array shape) of a
+/// concrete `UniformValue`, for comparing against what a shader declared.
+fn uniform_container(value: &UniformValue) -> shade::ContainerType {
+    match *value {
+        UniformValue::I32(_) | UniformValue::F32(_) | UniformValue::U32(_) |
+        UniformValue::Bool(_) | UniformValue::F64(_) =>
+            shade::ContainerType::Single,
+        UniformValue::I32Vector2(_) | UniformValue::F32Vector2(_) | UniformValue::U32Vector2(_) |
+        UniformValue::BoolVector2(_) | UniformValue::F64Vector2(_) =>
+            shade::ContainerType::Vector(2),
+        UniformValue::I32Vector3(_) | UniformValue::F32Vector3(_) | UniformValue::U32Vector3(_) |
+        UniformValue::BoolVector3(_) | UniformValue::F64Vector3(_) =>
+            shade::ContainerType::Vector(3),
+        UniformValue::I32Vector4(_) | UniformValue::F32Vector4(_) | UniformValue::U32Vector4(_) |
+        UniformValue::BoolVector4(_) | UniformValue::F64Vector4(_) =>
+            shade::ContainerType::Vector(4),
+        UniformValue::F32Matrix2(_) => shade::ContainerType::Matrix(2, 2),
+        UniformValue::F32Matrix3(_) => shade::ContainerType::Matrix(3, 3),
+        UniformValue::F32Matrix4(_) => shade::ContainerType::Matrix(4, 4),
+        UniformValue::F32Matrix2x3(_) => shade::ContainerType::Matrix(2, 3),
+        UniformValue::F32Matrix2x4(_) => shade::ContainerType::Matrix(2, 4),
+        UniformValue::F32Matrix3x2(_) => shade::ContainerType::Matrix(3, 2),
+        UniformValue::F32Matrix3x4(_) => shade::ContainerType::Matrix(3, 4),
+        UniformValue::F32Matrix4x2(_) => shade::ContainerType::Matrix(4, 2),
+        UniformValue::F32Matrix4x3(_) => shade::ContainerType::Matrix(4, 3),
+        // An empty array carries no element shape of its own; default to
+        // `Single` the same way `uniform_base_type` defaults to `F32`.
+        UniformValue::Array(ref values) => shade::ContainerType::Array(
+            values.len(),
+            Box::new(values.first().map_or(shade::ContainerType::Single, |v| uniform_container(v))),
+        ),
+    }
+}
+
 /// Parameter index.
 pub type ParameterId = u16;
 
@@ -87,16 +301,6 @@ pub trait Parameter<R: Resources> {
     fn put(&self, ParameterId, &mut ParamStorage<R>);
 }
 
-impl<T: Clone + Into<UniformValue>, R: Resources> Parameter<R> for T {
-    fn check_uniform(_var: &shade::UniformVar) -> bool {
-        true //TODO
-    }
-
-    fn put(&self, id: ParameterId, storage: &mut ParamStorage<R>) {
-        storage.uniforms[id as usize] = Some(self.clone().into());
-    }
-}
-
 impl<R: Resources> Parameter<R> for handle::RawBuffer<R> {
     fn check_block(_var: &shade::BlockVar) -> bool {
         true
@@ -117,6 +321,32 @@ impl<R: Resources> Parameter<R> for TextureParam<R> {
     }
 }
 
+impl<R: Resources> Parameter<R> for SampledTexture<R> {
+    // Only the multisample flag is a type-level property `check_texture`
+    // (no `&self`) can compare; see the struct's doc comment for why
+    // dimensionality isn't checked here too.
+    fn check_texture(var: &shade::SamplerVar) -> bool {
+        !var.is_multi
+    }
+
+    fn put(&self, id: ParameterId, storage: &mut ParamStorage<R>) {
+        storage.textures[id as usize] = Some((self.0.clone(), Some(self.1.clone())));
+    }
+}
+
+impl<R: Resources> Parameter<R> for UnsampledTexture<R> {
+    // Only the multisample flag is a type-level property `check_texture`
+    // (no `&self`) can compare; see `SampledTexture`'s doc comment for why
+    // dimensionality isn't checked here too.
+    fn check_texture(var: &shade::SamplerVar) -> bool {
+        var.is_multi
+    }
+
+    fn put(&self, id: ParameterId, storage: &mut ParamStorage<R>) {
+        storage.textures[id as usize] = Some((self.0.clone(), None));
+    }
+}
+
 
 /// Abstracts the shader parameter structure, generated by the `shader_param` attribute
 #[allow(missing_docs)]
@@ -124,17 +354,30 @@ pub trait ShaderParam {
     type Resources: Resources;
     /// A helper structure to contain variable indices inside the shader
     type Link: Clone;
-    /// Create a new link to be used with a given program
-    fn create_link(Option<&Self>, &shade::ProgramInfo) -> Result<Self::Link, ParameterError>;
-    /// Get all the contained parameter values, using a given link
-    fn fill_params(&self, &Self::Link, &mut ParamStorage<Self::Resources>);
+    /// Create a new link to be used with a given program. On success, also
+    /// returns any non-fatal warnings accumulated while linking (inactive
+    /// uniforms/blocks, near-miss type mismatches).
+    fn create_link(Option<&Self>, &shade::ProgramInfo)
+                   -> Result<(Self::Link, Vec<ProgramWarning>), ParameterError>;
+    /// Get all the contained parameter values, using a given link, writing
+    /// into `storage`. `storage_epoch` must be a value the caller changes
+    /// every time `storage` stops being the same logical `ParamStorage` this
+    /// link last filled (e.g. a per-draw or per-frame counter) — an impl
+    /// that caches per-cell writes (like `ParamDictionary`'s) uses a change
+    /// in `storage_epoch` to know it must rewrite every cell into the new
+    /// `storage` rather than trusting `storage`'s address, which a dropped
+    /// and reused allocation can make misleadingly "the same". Returns
+    /// `true` if anything actually changed in `ParamStorage` (and thus needs
+    /// to be re-uploaded), so callers can skip redundant state setup.
+    fn fill_params(&self, &Self::Link, storage_epoch: u64, &mut ParamStorage<Self::Resources>) -> bool;
 }
 
 impl<R: Resources> ShaderParam for Option<R> {
     type Resources = R;
     type Link = ();
 
-    fn create_link(_: Option<&Option<R>>, info: &shade::ProgramInfo) -> Result<(), ParameterError> {
+    fn create_link(_: Option<&Option<R>>, info: &shade::ProgramInfo)
+                   -> Result<((), Vec<ProgramWarning>), ParameterError> {
         match info.uniforms[..].first() {
             Some(u) => return Err(ParameterError::MissingUniform(u.name.clone())),
             None => (),
@@ -147,20 +390,81 @@ impl<R: Resources> ShaderParam for Option<R> {
             Some(t) => return Err(ParameterError::MissingTexture(t.name.clone())),
             None => (),
         }
-        Ok(())
+        Ok(((), Vec::new()))
     }
 
-    fn fill_params(&self, _: &(), _: &mut ParamStorage<R>) {
-        //empty
+    fn fill_params(&self, _: &(), _: u64, _: &mut ParamStorage<R>) -> bool {
+        false //empty
     }
 }
 
-/// A named cell containing arbitrary value
+/// A named cell containing an arbitrary value. Every mutation through
+/// `set`/`get_mut` bumps a generation counter, so a `ParamDictionaryLink`
+/// can tell whether a cell changed since it was last read without comparing
+/// the value itself.
 pub struct NamedCell<T> {
     /// Name
     pub name: String,
     /// Value
     pub value: RefCell<T>,
+    version: Cell<u32>,
+}
+
+impl<T> NamedCell<T> {
+    /// Wrap a named value, at generation 0.
+    pub fn new(name: String, value: T) -> NamedCell<T> {
+        NamedCell {
+            name: name,
+            value: RefCell::new(value),
+            version: Cell::new(0),
+        }
+    }
+
+    /// The current generation number of the cell.
+    pub fn version(&self) -> u32 {
+        self.version.get()
+    }
+
+    /// Replace the value, bumping the generation counter.
+    pub fn set(&self, value: T) {
+        *self.value.borrow_mut() = value;
+        self.version.set(self.version.get().wrapping_add(1));
+    }
+
+    /// Borrow the value mutably. The generation counter is bumped when the
+    /// returned guard is dropped.
+    pub fn get_mut(&self) -> NamedCellRefMut<T> {
+        NamedCellRefMut {
+            cell: self,
+            borrow: self.value.borrow_mut(),
+        }
+    }
+}
+
+/// A mutable borrow of a `NamedCell`'s value, obtained from `get_mut`.
+/// Bumps the cell's generation counter on drop.
+pub struct NamedCellRefMut<'a, T: 'a> {
+    cell: &'a NamedCell<T>,
+    borrow: RefMut<'a, T>,
+}
+
+impl<'a, T> Deref for NamedCellRefMut<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &*self.borrow
+    }
+}
+
+impl<'a, T> DerefMut for NamedCellRefMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut *self.borrow
+    }
+}
+
+impl<'a, T> Drop for NamedCellRefMut<'a, T> {
+    fn drop(&mut self) {
+        self.cell.version.set(self.cell.version.get().wrapping_add(1));
+    }
 }
 
 /// A dictionary of parameters, meant to be shared between different programs
@@ -173,12 +477,46 @@ pub struct ParamDictionary<R: Resources> {
     pub textures: Vec<NamedCell<TextureParam<R>>>,
 }
 
+/// A linked cell id paired with the generation of that cell last written
+/// into `ParamStorage`, so repeated `fill_params` calls can skip cells that
+/// haven't changed since.
+#[derive(Clone)]
+struct LinkedCell {
+    id: usize,
+    seen_version: Cell<u32>,
+}
+
+impl LinkedCell {
+    fn new(id: usize) -> LinkedCell {
+        // `u32::MAX` never matches a freshly created `NamedCell`'s
+        // generation 0, so the first `fill_params` always writes.
+        LinkedCell { id: id, seen_version: Cell::new(::std::u32::MAX) }
+    }
+}
+
 /// Redirects program input to the relevant ParamDictionary cell
 #[derive(Clone)]
 pub struct ParamDictionaryLink {
-    uniforms: Vec<usize>,
-    blocks: Vec<usize>,
-    textures: Vec<usize>,
+    uniforms: Vec<LinkedCell>,
+    blocks: Vec<LinkedCell>,
+    textures: Vec<LinkedCell>,
+    /// `storage_epoch` of the `ParamStorage` the generations above were last
+    /// written into. A `ParamDictionary` is shared across many draws, each
+    /// with its own (often freshly allocated) `ParamStorage`, so the cache
+    /// is only valid while `fill_params` keeps seeing the same epoch; a
+    /// changed epoch invalidates it (see `reset_cache`). This is keyed off
+    /// a caller-supplied epoch rather than `params`'s address, since a
+    /// dropped `ParamStorage`'s allocation can be reused by an unrelated
+    /// one, making address equality a false positive for "same storage".
+    last_epoch: Cell<u64>,
+}
+
+impl ParamDictionaryLink {
+    fn reset_cache(&self) {
+        for cell in self.uniforms.iter().chain(self.blocks.iter()).chain(self.textures.iter()) {
+            cell.seen_version.set(::std::u32::MAX);
+        }
+    }
 }
 
 impl<R: Resources> ShaderParam for ParamDictionary<R> {
@@ -186,34 +524,161 @@ impl<R: Resources> ShaderParam for ParamDictionary<R> {
     type Link = ParamDictionaryLink;
 
     fn create_link(this: Option<&ParamDictionary<R>>, info: &shade::ProgramInfo)
-                   -> Result<ParamDictionaryLink, ParameterError> {
+                   -> Result<(ParamDictionaryLink, Vec<ProgramWarning>), ParameterError> {
         let this = match this {
             Some(d) => d,
             None => return Err(ParameterError::MissingSelf),
         };
-        //TODO: proper error checks
-        Ok(ParamDictionaryLink {
-            uniforms: info.uniforms.iter().map(|var|
-                this.uniforms.iter().position(|c| c.name == var.name).unwrap()
-            ).collect(),
-            blocks: info.blocks.iter().map(|var|
-                this.blocks  .iter().position(|c| c.name == var.name).unwrap()
-            ).collect(),
-            textures: info.textures.iter().map(|var|
-                this.textures.iter().position(|c| c.name == var.name).unwrap()
-            ).collect(),
-        })
-    }
-
-    fn fill_params(&self, link: &ParamDictionaryLink, params: &mut ParamStorage<R>) {
-        for &id in link.uniforms.iter() {
-            params.uniforms[id] = Some(self.uniforms[id].value.borrow().clone());
-        }
-        for &id in link.blocks.iter() {
-            params.blocks[id] = Some(self.blocks[id].value.borrow().clone());
-        }
-        for &id in link.textures.iter() {
-            params.textures[id] = Some(self.textures[id].value.borrow().clone());
+        let mut warnings = Vec::new();
+        let mut uniforms = Vec::with_capacity(info.uniforms.len());
+        for var in info.uniforms.iter() {
+            match this.uniforms.iter().position(|c| c.name == var.name) {
+                Some(id) => {
+                    let value = this.uniforms[id].value.borrow();
+                    if uniform_container(&*value) != var.container {
+                        // Shape (scalar/vector/matrix/array) mismatches are
+                        // never safe to link against, unlike a near-miss
+                        // base type: the driver would read past or short of
+                        // the bytes we actually upload.
+                        return Err(ParameterError::BadUniform(var.name.clone()));
+                    }
+                    let found = uniform_base_type(&*value);
+                    if found != var.base_type {
+                        warnings.push(ProgramWarning::TypeMismatch {
+                            name: var.name.clone(),
+                            expected: var.base_type,
+                            found: found,
+                        });
+                    }
+                    uniforms.push(LinkedCell::new(id));
+                },
+                None => return Err(ParameterError::MissingUniform(var.name.clone())),
+            }
+        }
+        for cell in this.uniforms.iter() {
+            if !info.uniforms.iter().any(|var| var.name == cell.name) {
+                warnings.push(ProgramWarning::InactiveUniform(cell.name.clone()));
+            }
+        }
+        let mut blocks = Vec::with_capacity(info.blocks.len());
+        for var in info.blocks.iter() {
+            match this.blocks.iter().position(|c| c.name == var.name) {
+                Some(id) => blocks.push(LinkedCell::new(id)),
+                None => return Err(ParameterError::MissingBlock(var.name.clone())),
+            }
         }
+        for cell in this.blocks.iter() {
+            if !info.blocks.iter().any(|var| var.name == cell.name) {
+                warnings.push(ProgramWarning::InactiveBlock(cell.name.clone()));
+            }
+        }
+        let mut textures = Vec::with_capacity(info.textures.len());
+        for var in info.textures.iter() {
+            match this.textures.iter().position(|c| c.name == var.name) {
+                Some(id) => textures.push(LinkedCell::new(id)),
+                None => return Err(ParameterError::MissingTexture(var.name.clone())),
+            }
+        }
+        Ok((ParamDictionaryLink {
+            uniforms: uniforms,
+            blocks: blocks,
+            textures: textures,
+            // `u64::MAX` never matches a caller's first real epoch (assumed
+            // to start at 0 and increase), so the first `fill_params` call
+            // always treats the cache as invalid and writes every cell.
+            last_epoch: Cell::new(::std::u64::MAX),
+        }, warnings))
+    }
+
+    fn fill_params(&self, link: &ParamDictionaryLink, storage_epoch: u64, params: &mut ParamStorage<R>) -> bool {
+        if link.last_epoch.get() != storage_epoch {
+            link.reset_cache();
+            link.last_epoch.set(storage_epoch);
+        }
+        let mut changed = false;
+        for cell in link.uniforms.iter() {
+            let version = self.uniforms[cell.id].version();
+            if version != cell.seen_version.get() {
+                params.uniforms[cell.id] = Some(self.uniforms[cell.id].value.borrow().clone());
+                cell.seen_version.set(version);
+                changed = true;
+            }
+        }
+        for cell in link.blocks.iter() {
+            let version = self.blocks[cell.id].version();
+            if version != cell.seen_version.get() {
+                params.blocks[cell.id] = Some(self.blocks[cell.id].value.borrow().clone());
+                cell.seen_version.set(version);
+                changed = true;
+            }
+        }
+        for cell in link.textures.iter() {
+            let version = self.textures[cell.id].version();
+            if version != cell.seen_version.get() {
+                params.textures[cell.id] = Some(self.textures[cell.id].value.borrow().clone());
+                cell.seen_version.set(version);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UniformValue, uniform_base_type, uniform_container};
+    use device::shade;
+
+    // These two helpers are the crux of `ParamDictionary::create_link`'s
+    // validation: a wrong `uniform_base_type` hides a `TypeMismatch` warning
+    // (or raises a spurious one), and a wrong `uniform_container` would let `BadUniform`
+    // either fire on a legitimate match or never fire on a real shape
+    // mismatch. Both are plain functions over `UniformValue`, so they're
+    // cheap to pin down without a mock `Resources`/`ProgramInfo`.
+
+    #[test]
+    fn base_type_reflects_scalars_and_vectors() {
+        assert_eq!(uniform_base_type(&UniformValue::I32(0)), shade::BaseType::I32);
+        assert_eq!(uniform_base_type(&UniformValue::F32(0.0)), shade::BaseType::F32);
+        assert_eq!(uniform_base_type(&UniformValue::U32Vector3([0, 0, 0])), shade::BaseType::U32);
+        assert_eq!(uniform_base_type(&UniformValue::BoolVector2([false, false])), shade::BaseType::Bool);
+        assert_eq!(uniform_base_type(&UniformValue::F64Vector4([0.0; 4])), shade::BaseType::F64);
+    }
+
+    #[test]
+    fn base_type_reflects_matrices() {
+        assert_eq!(uniform_base_type(&UniformValue::F32Matrix4([[0.0; 4]; 4])), shade::BaseType::F32);
+        assert_eq!(uniform_base_type(&UniformValue::F32Matrix2x3([[0.0; 3]; 2])), shade::BaseType::F32);
+    }
+
+    #[test]
+    fn base_type_of_array_is_its_element_type() {
+        let array = UniformValue::Array(vec![UniformValue::U32(1), UniformValue::U32(2)]);
+        assert_eq!(uniform_base_type(&array), shade::BaseType::U32);
+    }
+
+    #[test]
+    fn base_type_of_empty_array_defaults_to_f32() {
+        let array = UniformValue::Array(Vec::new());
+        assert_eq!(uniform_base_type(&array), shade::BaseType::F32);
+    }
+
+    #[test]
+    fn container_reflects_shape() {
+        assert_eq!(uniform_container(&UniformValue::F32(0.0)), shade::ContainerType::Single);
+        assert_eq!(uniform_container(&UniformValue::I32Vector3([0, 0, 0])), shade::ContainerType::Vector(3));
+        assert_eq!(uniform_container(&UniformValue::F32Matrix3x2([[0.0; 2]; 3])), shade::ContainerType::Matrix(3, 2));
+    }
+
+    #[test]
+    fn container_of_array_carries_length_and_element_shape() {
+        let array = UniformValue::Array(vec![
+            UniformValue::F32Vector4([0.0; 4]),
+            UniformValue::F32Vector4([1.0; 4]),
+        ]);
+        assert_eq!(
+            uniform_container(&array),
+            shade::ContainerType::Array(2, Box::new(shade::ContainerType::Vector(4)))
+        );
     }
 }